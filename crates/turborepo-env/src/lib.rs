@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     env,
     ops::{Deref, DerefMut},
 };
@@ -96,44 +96,7 @@ impl EnvironmentVariableMap {
         &self,
         wildcard_patterns: &[String],
     ) -> Result<WildcardMaps, regex::Error> {
-        let mut output = WildcardMaps {
-            inclusions: EnvironmentVariableMap::default(),
-            exclusions: EnvironmentVariableMap::default(),
-        };
-
-        let mut include_patterns = Vec::new();
-        let mut exclude_patterns = Vec::new();
-
-        for wildcard_pattern in wildcard_patterns {
-            if wildcard_pattern.starts_with('!') {
-                let exclude_pattern = wildcard_to_regex_pattern(&wildcard_pattern[1..]);
-                exclude_patterns.push(exclude_pattern);
-            } else if wildcard_pattern.starts_with('\\')
-                && wildcard_pattern.chars().nth(1) == Some('!')
-            {
-                let include_pattern = wildcard_to_regex_pattern(&wildcard_pattern[1..]);
-                include_patterns.push(include_pattern);
-            } else {
-                let include_pattern = wildcard_to_regex_pattern(&wildcard_pattern);
-                include_patterns.push(include_pattern);
-            }
-        }
-
-        let include_regex_string = format!("^({})$", include_patterns.join("|"));
-        let exclude_regex_string = format!("^({})$", exclude_patterns.join("|"));
-
-        let include_regex = Regex::new(&include_regex_string)?;
-        let exclude_regex = Regex::new(&exclude_regex_string)?;
-        for (env_var, env_value) in &self.0 {
-            if !include_patterns.is_empty() && include_regex.is_match(env_var) {
-                output.inclusions.insert(env_var.clone(), env_value.clone());
-            }
-            if !exclude_patterns.is_empty() && exclude_regex.is_match(env_var) {
-                output.exclusions.insert(env_var.clone(), env_value.clone());
-            }
-        }
-
-        Ok(output)
+        Ok(EnvMatcher::new(wildcard_patterns)?.apply(self))
     }
 
     // Returns an EnvironmentVariableMap containing the variables
@@ -168,10 +131,323 @@ impl EnvironmentVariableMap {
     }
 }
 
+// A single include pattern's matcher, kept alongside the combined include
+// regex so `EnvMatcher::apply_with_provenance` can attribute a match back to
+// the pattern that produced it.
+enum IncludeSource {
+    Literal(String),
+    Regex(Regex),
+}
+
+impl IncludeSource {
+    // `folded_key` is `key` case-folded per the matcher's `case_insensitive`
+    // setting; `Regex` already bakes case-insensitivity into its own flags.
+    fn is_match(&self, key: &str, folded_key: &str) -> bool {
+        match self {
+            IncludeSource::Literal(literal) => literal == folded_key,
+            IncludeSource::Regex(regex) => regex.is_match(key),
+        }
+    }
+}
+
+// EnvMatcher compiles a set of wildcard patterns once so it can be reused
+// across many EnvironmentVariableMaps instead of recompiling regexes per
+// call.
+pub struct EnvMatcher {
+    include_literals: HashSet<String>,
+    exclude_literals: HashSet<String>,
+    include_regex: Option<Regex>,
+    exclude_regex: Option<Regex>,
+    include_sources: Vec<(usize, IncludeSource)>,
+    case_insensitive: bool,
+}
+
+// WildcardOptions configures how an `EnvMatcher` interprets its patterns.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WildcardOptions {
+    // Matches env var names case-insensitively, for Windows parity.
+    pub case_insensitive: bool,
+}
+
+impl EnvMatcher {
+    pub fn new(wildcard_patterns: &[String]) -> Result<Self, regex::Error> {
+        Self::with_options(wildcard_patterns, WildcardOptions::default())
+    }
+
+    pub fn with_options(
+        wildcard_patterns: &[String],
+        options: WildcardOptions,
+    ) -> Result<Self, regex::Error> {
+        let mut include_literals = HashSet::new();
+        let mut exclude_literals = HashSet::new();
+        let mut include_patterns = Vec::new();
+        let mut exclude_patterns = Vec::new();
+        let mut include_sources = Vec::new();
+
+        for (index, wildcard_pattern) in wildcard_patterns.iter().enumerate() {
+            let (is_exclude, rest) = split_negation(wildcard_pattern);
+            let (syntax, body) = split_pattern_syntax(rest);
+
+            let literal_or_pattern = match syntax {
+                PatternSyntax::Literal => Ok(body.to_string()),
+                PatternSyntax::Regexp => Err(body.to_string()),
+                PatternSyntax::Glob => {
+                    literal_pattern(body).ok_or_else(|| wildcard_to_regex_pattern(body))
+                }
+            };
+
+            match (is_exclude, literal_or_pattern) {
+                (true, Ok(literal)) => {
+                    exclude_literals.insert(fold_case(literal, options.case_insensitive));
+                }
+                (true, Err(pattern)) => exclude_patterns.push(pattern),
+                (false, Ok(literal)) => {
+                    let folded = fold_case(literal, options.case_insensitive);
+                    include_sources.push((index, IncludeSource::Literal(folded.clone())));
+                    include_literals.insert(folded);
+                }
+                (false, Err(pattern)) => {
+                    let regex = Regex::new(&format!(
+                        "{}^({})$",
+                        if options.case_insensitive { "(?i)" } else { "" },
+                        pattern
+                    ))?;
+                    include_sources.push((index, IncludeSource::Regex(regex)));
+                    include_patterns.push(pattern);
+                }
+            }
+        }
+
+        Ok(EnvMatcher {
+            include_literals,
+            exclude_literals,
+            include_regex: compile_combined(&include_patterns, options.case_insensitive)?,
+            exclude_regex: compile_combined(&exclude_patterns, options.case_insensitive)?,
+            include_sources,
+            case_insensitive: options.case_insensitive,
+        })
+    }
+
+    // Applies this matcher to `env`, returning the inclusions and exclusions it
+    // produces.
+    pub fn apply(&self, env: &EnvironmentVariableMap) -> WildcardMaps {
+        let mut output = WildcardMaps {
+            inclusions: EnvironmentVariableMap::default(),
+            exclusions: EnvironmentVariableMap::default(),
+        };
+
+        if self.case_insensitive {
+            if !self.include_literals.is_empty() || !self.exclude_literals.is_empty() {
+                // Fold every env key once up front so literal lookups stay O(1)
+                // per pattern instead of scanning the environment per pattern.
+                // Assumes `env` never has two keys that fold to the same value
+                // (e.g. both "PATH" and "Path"), which doesn't happen for a
+                // real single-OS process environment; if it ever did, one of
+                // the colliding keys would be silently dropped here.
+                let folded_keys: HashMap<String, &String> = env
+                    .0
+                    .keys()
+                    .map(|key| (fold_case(key.clone(), true), key))
+                    .collect();
+
+                for literal in &self.include_literals {
+                    if let Some(&key) = folded_keys.get(literal) {
+                        output.inclusions.insert(key.clone(), env.0[key].clone());
+                    }
+                }
+                for literal in &self.exclude_literals {
+                    if let Some(&key) = folded_keys.get(literal) {
+                        output.exclusions.insert(key.clone(), env.0[key].clone());
+                    }
+                }
+            }
+        } else {
+            for key in &self.include_literals {
+                if let Some(value) = env.0.get(key) {
+                    output.inclusions.insert(key.clone(), value.clone());
+                }
+            }
+            for key in &self.exclude_literals {
+                if let Some(value) = env.0.get(key) {
+                    output.exclusions.insert(key.clone(), value.clone());
+                }
+            }
+        }
+
+        if self.include_regex.is_some() || self.exclude_regex.is_some() {
+            for (env_var, env_value) in &env.0 {
+                if self
+                    .include_regex
+                    .as_ref()
+                    .is_some_and(|re| re.is_match(env_var))
+                {
+                    output.inclusions.insert(env_var.clone(), env_value.clone());
+                }
+                if self
+                    .exclude_regex
+                    .as_ref()
+                    .is_some_and(|re| re.is_match(env_var))
+                {
+                    output.exclusions.insert(env_var.clone(), env_value.clone());
+                }
+            }
+        }
+
+        output
+    }
+
+    // Like `apply`, but also returns a map from each included variable name to
+    // the indices (into the `wildcard_patterns` this matcher was built from) of
+    // every inclusion pattern that matched it. Powers the Dry Run Summary,
+    // which shows which `globalEnv`/`env` entry is responsible for a variable
+    // being hashed.
+    pub fn apply_with_provenance(
+        &self,
+        env: &EnvironmentVariableMap,
+    ) -> (WildcardMaps, HashMap<String, Vec<usize>>) {
+        let maps = self.apply(env);
+
+        let mut provenance = HashMap::new();
+        for key in maps.inclusions.0.keys() {
+            // Excluded vars never make it into the hash (they're netted out by
+            // `resolve`), so they shouldn't show up as "why was this hashed"
+            // provenance either.
+            if maps.exclusions.0.contains_key(key) {
+                continue;
+            }
+
+            let folded_key = fold_case(key.clone(), self.case_insensitive);
+            let indices: Vec<usize> = self
+                .include_sources
+                .iter()
+                .filter(|(_, source)| source.is_match(key, &folded_key))
+                .map(|(index, _)| *index)
+                .collect();
+            if !indices.is_empty() {
+                provenance.insert(key.clone(), indices);
+            }
+        }
+
+        (maps, provenance)
+    }
+
+    // Returns whether `key` is included by this matcher, honoring exclusions.
+    // Useful when callers only need a yes/no answer for a single variable, e.g.
+    // deciding whether to pass it through to a task.
+    pub fn is_match(&self, key: &str) -> bool {
+        let folded_key = fold_case(key.to_string(), self.case_insensitive);
+
+        let included = self.include_literals.contains(&folded_key)
+            || self
+                .include_regex
+                .as_ref()
+                .is_some_and(|re| re.is_match(key));
+        if !included {
+            return false;
+        }
+
+        let excluded = self.exclude_literals.contains(&folded_key)
+            || self
+                .exclude_regex
+                .as_ref()
+                .is_some_and(|re| re.is_match(key));
+        !excluded
+    }
+}
+
+// Case-folds `value` when `case_insensitive` is set, otherwise returns it
+// unchanged.
+fn fold_case(value: String, case_insensitive: bool) -> String {
+    if case_insensitive {
+        value.to_lowercase()
+    } else {
+        value
+    }
+}
+
+// Splits a leading `!` (exclude) or `\!` (escaped, literal include) prefix off
+// of `pattern`, returning whether the pattern is an exclusion and the
+// remainder to be parsed further.
+fn split_negation(pattern: &str) -> (bool, &str) {
+    if let Some(rest) = pattern.strip_prefix('!') {
+        (true, rest)
+    } else if pattern.starts_with('\\') && pattern.chars().nth(1) == Some('!') {
+        (false, &pattern[1..])
+    } else {
+        (false, pattern)
+    }
+}
+
+// PatternSyntax selects how the body of a pattern (after negation has been
+// stripped) is interpreted.
+enum PatternSyntax {
+    // `literal:NAME` matches NAME exactly, bypassing the regex engine entirely.
+    Literal,
+    // `glob:PAT` matches using `*` wildcards. This is the default syntax when a
+    // pattern carries no recognized prefix, for backward compatibility.
+    Glob,
+    // `regexp:PAT` treats PAT as a raw regex fragment, inserted verbatim into
+    // the combined include/exclude regex.
+    Regexp,
+}
+
+// Strips a `literal:`, `glob:`, or `regexp:` prefix off of `pattern`,
+// returning the syntax it selects and the remaining body. Patterns with no
+// recognized prefix default to `Glob`.
+fn split_pattern_syntax(pattern: &str) -> (PatternSyntax, &str) {
+    if let Some(body) = pattern.strip_prefix("literal:") {
+        (PatternSyntax::Literal, body)
+    } else if let Some(body) = pattern.strip_prefix("regexp:") {
+        (PatternSyntax::Regexp, body)
+    } else if let Some(body) = pattern.strip_prefix("glob:") {
+        (PatternSyntax::Glob, body)
+    } else {
+        (PatternSyntax::Glob, pattern)
+    }
+}
+
+// Compiles `patterns` into a single combined `^(p1|p2|...)$` regex, or
+// `None` if there are no patterns to avoid paying for an always-empty match.
+fn compile_combined(
+    patterns: &[String],
+    case_insensitive: bool,
+) -> Result<Option<Regex>, regex::Error> {
+    if patterns.is_empty() {
+        Ok(None)
+    } else {
+        let flags = if case_insensitive { "(?i)" } else { "" };
+        Ok(Some(Regex::new(&format!(
+            "{}^({})$",
+            flags,
+            patterns.join("|")
+        ))?))
+    }
+}
+
 const WILDCARD: char = '*';
 const WILDCARD_ESCAPE: char = '\\';
 const REGEX_WILDCARD_SEGMENT: &str = ".*";
 
+// Returns `Some` with escaped asterisks unescaped to plain `*` if `pattern`
+// contains no unescaped wildcard, i.e. it can be matched with a direct
+// lookup rather than a regex. Returns `None` if the pattern contains a real
+// wildcard and must go through `wildcard_to_regex_pattern` instead.
+fn literal_pattern(pattern: &str) -> Option<String> {
+    let mut literal = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars().peekable();
+    while let Some(char) = chars.next() {
+        if char == WILDCARD_ESCAPE && chars.peek() == Some(&WILDCARD) {
+            literal.push(WILDCARD);
+            chars.next();
+        } else if char == WILDCARD {
+            return None;
+        } else {
+            literal.push(char);
+        }
+    }
+    Some(literal)
+}
+
 fn wildcard_to_regex_pattern(pattern: &str) -> String {
     let mut regex_string = Vec::new();
     let mut previous_index = 0;
@@ -224,4 +500,126 @@ mod tests {
         let actual = super::wildcard_to_regex_pattern(pattern);
         assert_eq!(actual, expected);
     }
+
+    #[test_case("FOO", Some("FOO".to_string()) ; "plain name")]
+    #[test_case("LITERAL_\\*", Some("LITERAL_*".to_string()) ; "escaped wildcard is literal")]
+    #[test_case("FOO*", None ; "wildcard is not literal")]
+    fn test_literal_pattern(pattern: &str, expected: Option<String>) {
+        let actual = super::literal_pattern(pattern);
+        assert_eq!(actual, expected);
+    }
+
+    #[test_case(&["FOO".to_string()], "FOO", true ; "literal match")]
+    #[test_case(&["FOO".to_string()], "BAR", false ; "literal miss")]
+    #[test_case(&["FOO_*".to_string()], "FOO_BAR", true ; "wildcard match")]
+    #[test_case(&["literal:FOO*".to_string()], "FOO*", true ; "literal prefix matches the star itself")]
+    #[test_case(&["literal:FOO*".to_string()], "FOOBAR", false ; "literal prefix does not glob")]
+    #[test_case(&["glob:FOO_*".to_string()], "FOO_BAR", true ; "explicit glob prefix")]
+    #[test_case(&["AWS_*".to_string(), "!AWS_SECRET".to_string()], "AWS_SECRET", false ; "exclusion wins over inclusion")]
+    #[test_case(&["AWS_*".to_string(), "!AWS_SECRET".to_string()], "AWS_REGION", true ; "non-excluded entry still matches")]
+    fn test_env_matcher_is_match(patterns: &[String], key: &str, expected: bool) {
+        let matcher = super::EnvMatcher::new(patterns).unwrap();
+        assert_eq!(matcher.is_match(key), expected);
+    }
+
+    #[test]
+    fn test_env_matcher_regexp_prefix_composes_with_negation() {
+        let env: EnvironmentVariableMap = HashMap::from([
+            ("AWS_REGION".to_string(), "us-east-1".to_string()),
+            ("AWS_SECRET".to_string(), "shh".to_string()),
+        ])
+        .into();
+        let matcher =
+            super::EnvMatcher::new(&["regexp:AWS_.*".to_string(), "!regexp:.*_SECRET".to_string()])
+                .unwrap();
+
+        let resolved = matcher.apply(&env).resolve();
+        assert!(resolved.contains_key("AWS_REGION"));
+        assert!(!resolved.contains_key("AWS_SECRET"));
+    }
+
+    #[test_case(&["PATH".to_string()], "Path", false ; "case-sensitive by default")]
+    fn test_env_matcher_case_sensitivity_default(patterns: &[String], key: &str, expected: bool) {
+        let matcher = super::EnvMatcher::new(patterns).unwrap();
+        assert_eq!(matcher.is_match(key), expected);
+    }
+
+    #[test]
+    fn test_env_matcher_case_insensitive_matches_literal_and_glob() {
+        let matcher = super::EnvMatcher::with_options(
+            &["PATH".to_string(), "AWS_*".to_string()],
+            super::WildcardOptions {
+                case_insensitive: true,
+            },
+        )
+        .unwrap();
+
+        assert!(matcher.is_match("Path"));
+        assert!(matcher.is_match("aws_region"));
+
+        let env: EnvironmentVariableMap =
+            HashMap::from([("Path".to_string(), "/usr/bin".to_string())]).into();
+        let resolved = matcher.apply(&env).resolve();
+        assert!(resolved.contains_key("Path"));
+    }
+
+    #[test]
+    fn test_apply_with_provenance() {
+        let env: EnvironmentVariableMap = HashMap::from([
+            ("FOO".to_string(), "1".to_string()),
+            ("BAR".to_string(), "2".to_string()),
+            ("FOO_BAR".to_string(), "3".to_string()),
+        ])
+        .into();
+
+        let patterns = vec!["FOO".to_string(), "FOO_*".to_string(), "!BAR".to_string()];
+        let matcher = super::EnvMatcher::new(&patterns).unwrap();
+        let (maps, provenance) = matcher.apply_with_provenance(&env);
+        let resolved = maps.resolve();
+
+        assert!(resolved.contains_key("FOO"));
+        assert!(resolved.contains_key("FOO_BAR"));
+        assert!(!resolved.contains_key("BAR"));
+
+        assert_eq!(provenance.get("FOO"), Some(&vec![0]));
+        assert_eq!(provenance.get("FOO_BAR"), Some(&vec![1]));
+        assert_eq!(provenance.get("BAR"), None);
+    }
+
+    #[test]
+    fn test_apply_with_provenance_excludes_overrule_inclusion_matches() {
+        let env: EnvironmentVariableMap = HashMap::from([
+            ("AWS_SECRET".to_string(), "shh".to_string()),
+            ("AWS_REGION".to_string(), "us-east-1".to_string()),
+        ])
+        .into();
+
+        let patterns = vec!["AWS_*".to_string(), "!AWS_SECRET".to_string()];
+        let matcher = super::EnvMatcher::new(&patterns).unwrap();
+        let (maps, provenance) = matcher.apply_with_provenance(&env);
+        let resolved = maps.resolve();
+
+        assert!(!resolved.contains_key("AWS_SECRET"));
+        assert!(resolved.contains_key("AWS_REGION"));
+
+        assert_eq!(provenance.get("AWS_SECRET"), None);
+        assert_eq!(provenance.get("AWS_REGION"), Some(&vec![0]));
+    }
+
+    #[test]
+    fn test_apply_with_provenance_respects_case_insensitivity() {
+        let env: EnvironmentVariableMap =
+            HashMap::from([("Path".to_string(), "/usr/bin".to_string())]).into();
+
+        let matcher = super::EnvMatcher::with_options(
+            &["PATH".to_string()],
+            super::WildcardOptions {
+                case_insensitive: true,
+            },
+        )
+        .unwrap();
+        let (_, provenance) = matcher.apply_with_provenance(&env);
+
+        assert_eq!(provenance.get("Path"), Some(&vec![0]));
+    }
 }
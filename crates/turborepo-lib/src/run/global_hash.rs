@@ -2,14 +2,11 @@ use std::{collections::HashMap, string::ToString};
 
 use anyhow::Result;
 use turbopath::RelativeUnixPathBuf;
+use turborepo_env::{BySource, DetailedMap, EnvMatcher, EnvironmentVariableMap, WildcardOptions};
 use turborepo_lockfile::Lockfile;
 
 use crate::{
-    cli::EnvMode,
-    commands::CommandBase,
-    env::{BySource, DetailedMap, EnvironmentVariableMap},
-    package_json::PackageJson,
-    package_manager::PackageManager,
+    cli::EnvMode, commands::CommandBase, package_json::PackageJson, package_manager::PackageManager,
 };
 
 static DEFAULT_ENV_VARS: [String; 1] = ["VERCEL_ANALYTICS_ID".to_string()];
@@ -21,6 +18,10 @@ struct GlobalHashableInputs {
     root_external_deps_hash: String,
     env: Vec<String>,
     resolved_env_vars: DetailedMap,
+    // Maps each hashed global env var to the indices of the `global_env`
+    // patterns that matched it, so the Dry Run Summary can show which
+    // `globalEnv` entry is responsible for a given variable being hashed.
+    global_env_provenance: HashMap<String, Vec<usize>>,
     pass_through_env: Vec<String>,
     env_mode: EnvMode,
     framework_inference: bool,
@@ -42,7 +43,18 @@ fn get_global_hash_inputs(
 ) -> Result<GlobalHashableInputs> {
     let default_env_var_map = env_at_execution_start.from_wildcards(&DEFAULT_ENV_VARS[..])?;
 
-    let user_env_var_set = env_at_execution_start.from_wildcards_unresolved(&global_env)?;
+    // Env var names are case-insensitive on Windows, so match `global_env`
+    // accordingly there to keep task hashes deterministic across platforms.
+    // The `EnvMatcher` is built here and applied once, to the environment at
+    // execution start.
+    let global_env_matcher = EnvMatcher::with_options(
+        &global_env,
+        WildcardOptions {
+            case_insensitive: cfg!(target_os = "windows"),
+        },
+    )?;
+    let (user_env_var_set, global_env_provenance) =
+        global_env_matcher.apply_with_provenance(env_at_execution_start);
 
     let mut all_env_var_map = EnvironmentVariableMap::default();
     all_env_var_map.union(&user_env_var_set.inclusions);
@@ -67,6 +79,7 @@ fn get_global_hash_inputs(
 
     Ok(GlobalHashableInputs {
         resolved_env_vars: global_hashable_env_vars,
+        global_env_provenance,
         ..GlobalHashableInputs::default()
     })
 }